@@ -0,0 +1,279 @@
+//
+// Copyright 2023 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod native_handler;
+
+use std::{error::Error, sync::Arc};
+
+use anyhow::Context;
+use oak_crypto::encryption_key::AsyncEncryptionKeyHandle;
+use opentelemetry::metrics::Meter;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+use tokio_stream::{Stream, StreamExt};
+use tonic::transport::server::Connected;
+
+/// Implemented by each Oak Functions handler backend (Wasm, native, ...). A `Handler` is created
+/// once per process, wrapping whatever key material and long-lived state it needs, and is then
+/// shared across every connection `serve`/`serve_streaming` accepts.
+pub trait Handler: Send + Sync + Sized + 'static {
+    fn create(
+        encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
+        meter: Meter,
+    ) -> impl std::future::Future<Output = anyhow::Result<Self>> + Send;
+
+    /// Handles a single request frame, returning the single response frame to send back.
+    fn handle_invoke(
+        &self,
+        request: Vec<u8>,
+    ) -> impl std::future::Future<Output = anyhow::Result<Vec<u8>>> + Send;
+}
+
+/// Implemented by handlers that can produce more than one response chunk per request, e.g. for
+/// incremental/large payloads, progressive results, or long-running native handlers (log
+/// tailing, chunked inference output, file transfer).
+pub trait StreamingHandler: Handler {
+    /// Handles a single request frame, returning a channel that yields each response chunk as it
+    /// becomes available. The channel is closed once the response is complete.
+    fn handle_invoke_streaming(
+        &self,
+        request: Vec<u8>,
+    ) -> impl std::future::Future<Output = anyhow::Result<mpsc::Receiver<anyhow::Result<Vec<u8>>>>>
+           + Send;
+}
+
+/// Reads one length-delimited frame (a little-endian `u32` length followed by that many bytes)
+/// from `stream`, or `Ok(None)` if the connection was closed before a new frame started.
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> std::io::Result<Option<Vec<u8>>> {
+    let mut len_bytes = [0u8; 4];
+    match stream.read_exact(&mut len_bytes).await {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    }
+    let mut frame = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    stream.read_exact(&mut frame).await?;
+    Ok(Some(frame))
+}
+
+/// Writes one length-delimited frame to `stream`, matching `read_frame`'s wire format.
+async fn write_frame<S: AsyncWrite + Unpin>(stream: &mut S, frame: &[u8]) -> std::io::Result<()> {
+    stream.write_all(&(frame.len() as u32).to_le_bytes()).await?;
+    stream.write_all(frame).await?;
+    Ok(())
+}
+
+/// Tags a streamed item as a response chunk, carrying a `read_frame`/`write_frame`-framed payload.
+const STREAM_ITEM_CHUNK: u8 = 0;
+/// Tags a streamed item as the end-of-response marker, carrying no payload.
+const STREAM_ITEM_END: u8 = 1;
+
+/// Writes one response chunk of a streamed response. Distinct from `write_stream_end` at the
+/// wire level (a leading tag byte, not an empty length-delimited frame) so a handler that
+/// legitimately emits an empty chunk can never be confused with the end of the stream.
+async fn write_stream_chunk<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    chunk: &[u8],
+) -> std::io::Result<()> {
+    stream.write_all(&[STREAM_ITEM_CHUNK]).await?;
+    write_frame(stream, chunk).await
+}
+
+/// Writes the marker that ends a request's response stream.
+async fn write_stream_end<S: AsyncWrite + Unpin>(stream: &mut S) -> std::io::Result<()> {
+    stream.write_all(&[STREAM_ITEM_END]).await
+}
+
+/// Reads one item of a streamed response: `Some(chunk)` for a response chunk, or `None` once the
+/// end-of-stream marker is read.
+async fn read_stream_item<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> std::io::Result<Option<Vec<u8>>> {
+    let mut tag = [0u8; 1];
+    stream.read_exact(&mut tag).await?;
+    if tag[0] == STREAM_ITEM_END {
+        return Ok(None);
+    }
+    let mut len_bytes = [0u8; 4];
+    stream.read_exact(&mut len_bytes).await?;
+    let mut chunk = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    stream.read_exact(&mut chunk).await?;
+    Ok(Some(chunk))
+}
+
+/// Serves unary request/response traffic on `stream`: each accepted connection is read as a
+/// sequence of request frames, each dispatched to `handler` and answered with exactly one
+/// response frame, until the connection is closed.
+pub async fn serve<C, E, H>(
+    mut stream: Box<dyn Stream<Item = Result<C, E>> + Send + Unpin>,
+    encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
+    meter: Meter,
+) -> anyhow::Result<()>
+where
+    C: Connected + AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    E: Error + Send + Sync + 'static,
+    H: Handler,
+{
+    let handler = Arc::new(
+        H::create(encryption_key_handle, meter)
+            .await
+            .context("couldn't create handler")?,
+    );
+
+    while let Some(connection) = stream.next().await {
+        let mut connection = match connection {
+            Ok(connection) => connection,
+            Err(error) => {
+                eprintln!("failed to accept connection: {error}");
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            loop {
+                let request = match read_frame(&mut connection).await {
+                    Ok(Some(request)) => request,
+                    Ok(None) => return,
+                    Err(error) => {
+                        eprintln!("failed to read request: {error}");
+                        return;
+                    }
+                };
+                let response = match handler.handle_invoke(request).await {
+                    Ok(response) => response,
+                    Err(error) => {
+                        eprintln!("failed to handle request: {error:?}");
+                        return;
+                    }
+                };
+                if let Err(error) = write_frame(&mut connection, &response).await {
+                    eprintln!("failed to write response: {error}");
+                    return;
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// As `serve`, but each request frame is answered with zero or more response chunk items
+/// followed by an end-of-stream marker (see `write_stream_chunk`/`write_stream_end`), rather than
+/// exactly one frame.
+pub async fn serve_streaming<C, E, H>(
+    mut stream: Box<dyn Stream<Item = Result<C, E>> + Send + Unpin>,
+    encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
+    meter: Meter,
+) -> anyhow::Result<()>
+where
+    C: Connected + AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    E: Error + Send + Sync + 'static,
+    H: StreamingHandler,
+{
+    let handler = Arc::new(
+        H::create(encryption_key_handle, meter)
+            .await
+            .context("couldn't create handler")?,
+    );
+
+    while let Some(connection) = stream.next().await {
+        let mut connection = match connection {
+            Ok(connection) => connection,
+            Err(error) => {
+                eprintln!("failed to accept connection: {error}");
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            loop {
+                let request = match read_frame(&mut connection).await {
+                    Ok(Some(request)) => request,
+                    Ok(None) => return,
+                    Err(error) => {
+                        eprintln!("failed to read request: {error}");
+                        return;
+                    }
+                };
+                let mut chunks = match handler.handle_invoke_streaming(request).await {
+                    Ok(chunks) => chunks,
+                    Err(error) => {
+                        eprintln!("failed to handle streaming request: {error:?}");
+                        return;
+                    }
+                };
+                while let Some(chunk) = chunks.recv().await {
+                    let chunk = match chunk {
+                        Ok(chunk) => chunk,
+                        Err(error) => {
+                            eprintln!("streaming handler reported an error: {error:?}");
+                            return;
+                        }
+                    };
+                    if let Err(error) = write_stream_chunk(&mut connection, &chunk).await {
+                        eprintln!("failed to write response chunk: {error}");
+                        return;
+                    }
+                }
+                if let Err(error) = write_stream_end(&mut connection).await {
+                    eprintln!("failed to write end-of-response marker: {error}");
+                    return;
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn frame_round_trips_through_the_wire_format() {
+        let (mut writer, mut reader) = tokio::io::duplex(64);
+        write_frame(&mut writer, b"hello").await.unwrap();
+        assert_eq!(read_frame(&mut reader).await.unwrap(), Some(b"hello".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn empty_frame_round_trips() {
+        let (mut writer, mut reader) = tokio::io::duplex(64);
+        write_frame(&mut writer, b"").await.unwrap();
+        assert_eq!(read_frame(&mut reader).await.unwrap(), Some(Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn read_frame_returns_none_on_a_clean_close() {
+        let (writer, mut reader) = tokio::io::duplex(64);
+        drop(writer);
+        assert_eq!(read_frame(&mut reader).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn empty_stream_chunk_is_distinguishable_from_the_end_marker() {
+        let (mut writer, mut reader) = tokio::io::duplex(64);
+        write_stream_chunk(&mut writer, b"").await.unwrap();
+        write_stream_chunk(&mut writer, b"more").await.unwrap();
+        write_stream_end(&mut writer).await.unwrap();
+
+        assert_eq!(read_stream_item(&mut reader).await.unwrap(), Some(Vec::new()));
+        assert_eq!(read_stream_item(&mut reader).await.unwrap(), Some(b"more".to_vec()));
+        assert_eq!(read_stream_item(&mut reader).await.unwrap(), None);
+    }
+}
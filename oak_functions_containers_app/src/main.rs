@@ -16,7 +16,7 @@
 use std::{
     error::Error,
     fmt::Display,
-    net::{IpAddr, Ipv4Addr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     sync::Arc,
     time::Duration,
 };
@@ -28,11 +28,13 @@ use oak_containers_sdk::{InstanceEncryptionKeyHandle, OrchestratorClient};
 use oak_crypto::encryption_key::AsyncEncryptionKeyHandle;
 #[cfg(feature = "native")]
 use oak_functions_containers_app::native_handler::NativeHandler;
-use oak_functions_containers_app::serve as app_serve;
+use oak_functions_containers_app::{
+    serve as app_serve, serve_streaming as app_serve_streaming,
+};
 use oak_functions_service::{
     proto::oak::functions::config::{
         application_config::CommunicationChannel, ApplicationConfig, HandlerType,
-        TcpCommunicationChannel,
+        RendezvousCommunicationChannel, TcpCommunicationChannel, TlsCommunicationChannel,
     },
     wasm::wasmtime::WasmtimeHandler,
 };
@@ -42,12 +44,19 @@ use opentelemetry::{
     KeyValue,
 };
 use prost::Message;
+use rand::Rng;
+use rustls_pemfile::Item;
 use tokio::{
-    io::{AsyncRead, AsyncWrite},
-    net::TcpListener,
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
     runtime::Handle,
+    sync::mpsc,
+};
+use tokio_rustls::{rustls, TlsAcceptor};
+use tokio_stream::{
+    wrappers::{ReceiverStream, TcpListenerStream},
+    StreamExt,
 };
-use tokio_stream::wrappers::TcpListenerStream;
 use tokio_vsock::{VsockAddr, VsockListener};
 use tonic::transport::server::Connected;
 
@@ -96,6 +105,280 @@ where
     }
 }
 
+// As `serve`, but each request frame is answered with a stream of response chunks over the same
+// connection, rather than exactly one response frame. Used for large payloads, progressive
+// results, and long-running native handlers (log tailing, chunked inference output, file
+// transfer). Input is not streamed: each request is a single frame, handled as its own
+// invocation.
+async fn serve_streaming<S>(
+    addr: S,
+    handler_type: HandlerType,
+    stream: Box<
+        dyn tokio_stream::Stream<
+                Item = Result<
+                    impl Connected + AsyncRead + AsyncWrite + Send + Unpin + 'static,
+                    impl Error + Send + Sync + 'static,
+                >,
+            > + Send
+            + Unpin,
+    >,
+    encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
+    meter: Meter,
+) -> anyhow::Result<()>
+where
+    S: Display,
+{
+    eprintln!("Running Oak Functions (streaming) on Oak Containers at address: {addr}");
+
+    match handler_type {
+        HandlerType::HandlerUnspecified | HandlerType::HandlerWasm => {
+            // `WasmtimeHandler` doesn't implement `StreamingHandler`, so reject this combination
+            // with a clear error rather than reaching an unsatisfiable trait bound at compile
+            // time for the default handler type.
+            Err(anyhow!(
+                "the application config requested streaming, but the Wasm handler doesn't support it"
+            ))
+        }
+        HandlerType::HandlerNative => {
+            if cfg!(feature = "native") {
+                app_serve_streaming::<NativeHandler>(stream, encryption_key_handle, meter).await
+            } else {
+                panic!("Application config specified `native` handler type, but this binary does not support that feature");
+            }
+        }
+    }
+}
+
+// Dispatches to the unary or streaming `serve` entry point depending on `application_config`'s
+// `streaming` flag, so each `CommunicationChannel` arm doesn't need to duplicate the branch.
+async fn dispatch_serve<S>(
+    streaming: bool,
+    addr: S,
+    handler_type: HandlerType,
+    stream: Box<
+        dyn tokio_stream::Stream<
+                Item = Result<
+                    impl Connected + AsyncRead + AsyncWrite + Send + Unpin + 'static,
+                    impl Error + Send + Sync + 'static,
+                >,
+            > + Send
+            + Unpin,
+    >,
+    encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
+    meter: Meter,
+) -> anyhow::Result<()>
+where
+    S: Display,
+{
+    if streaming {
+        serve_streaming(addr, handler_type, stream, encryption_key_handle, meter).await
+    } else {
+        serve(addr, handler_type, stream, encryption_key_handle, meter).await
+    }
+}
+
+// Binds the TCP channel described by `config`. When `bind_address` is unset, this listens on
+// both `0.0.0.0` and `::` so the app is reachable from dual-stack and IPv6-only pod networks
+// without reconfiguration, merging the resulting accept streams into the single boxed stream
+// `serve` expects.
+async fn bind_tcp_channel(
+    config: &TcpCommunicationChannel,
+) -> anyhow::Result<(
+    SocketAddr,
+    Box<dyn tokio_stream::Stream<Item = std::io::Result<tokio::net::TcpStream>> + Send + Unpin>,
+)> {
+    let port = config.port.try_into()?;
+
+    if !config.bind_address.is_empty() {
+        let ip: IpAddr = config
+            .bind_address
+            .parse()
+            .context("couldn't parse bind_address")?;
+        let addr = SocketAddr::new(ip, port);
+        let listener = TcpListener::bind(addr).await?;
+        return Ok((addr, Box::new(TcpListenerStream::new(listener))));
+    }
+
+    let v4_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
+    let v6_addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port);
+    let socket = match socket2::Socket::new(
+        socket2::Domain::IPV6,
+        socket2::Type::STREAM,
+        Some(socket2::Protocol::TCP),
+    ) {
+        Ok(socket) => socket,
+        Err(_) => {
+            // IPv6 sockets can't be created on this host at all; listen on IPv4 only instead of
+            // failing to start.
+            let v4_listener = TcpListener::bind(v4_addr).await?;
+            return Ok((v4_addr, Box::new(TcpListenerStream::new(v4_listener))));
+        }
+    };
+    // Where the platform allows it, disable IPV6_V6ONLY so a single `::` socket also accepts
+    // IPv4-mapped connections, rather than binding two sockets.
+    if socket.set_only_v6(false).is_ok() {
+        match socket.bind(&v6_addr.into()) {
+            Ok(()) => {
+                socket.listen(1024)?;
+                socket.set_nonblocking(true)?;
+                let listener = TcpListener::from_std(socket.into())?;
+                return Ok((v6_addr, Box::new(TcpListenerStream::new(listener))));
+            }
+            Err(_) => {
+                // An IPv6 socket could be created here but not bound to `::`. Fall back to
+                // IPv4 only instead of failing to start.
+                let v4_listener = TcpListener::bind(v4_addr).await?;
+                return Ok((v4_addr, Box::new(TcpListenerStream::new(v4_listener))));
+            }
+        }
+    }
+
+    // The platform requires separate IPv4 and IPv6 sockets; bind both and merge their accept
+    // streams so callers still see a single stream. If IPv6 isn't usable here either, fall back
+    // to IPv4 only rather than failing to start.
+    let v4_listener = TcpListener::bind(v4_addr).await?;
+    match TcpListener::bind(v6_addr).await {
+        Ok(v6_listener) => {
+            let merged =
+                TcpListenerStream::new(v4_listener).merge(TcpListenerStream::new(v6_listener));
+            Ok((v4_addr, Box::new(merged)))
+        }
+        Err(_) => Ok((v4_addr, Box::new(TcpListenerStream::new(v4_listener)))),
+    }
+}
+
+const RENDEZVOUS_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const RENDEZVOUS_MAX_BACKOFF: Duration = Duration::from_secs(60);
+const RENDEZVOUS_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+// A control connection that stays up at least this long is considered healthy, so a later
+// disconnect starts reconnecting from `RENDEZVOUS_INITIAL_BACKOFF` again instead of resuming from
+// wherever backoff had escalated to before.
+const RENDEZVOUS_BACKOFF_RESET_THRESHOLD: Duration = Duration::from_secs(30);
+
+// Dials `config.relay_addr` and maintains a persistent control connection that the relay uses to
+// signal new client connections, handing each one to `serve` as one item of the returned stream.
+// This lets the app run behind NAT or in networks where inbound binding is impossible, since it
+// never itself binds a listener.
+fn rendezvous_stream(
+    config: RendezvousCommunicationChannel,
+) -> Box<dyn tokio_stream::Stream<Item = std::io::Result<TcpStream>> + Send + Unpin> {
+    let (sender, receiver) = mpsc::channel(16);
+    tokio::spawn(async move {
+        let mut backoff = RENDEZVOUS_INITIAL_BACKOFF;
+        loop {
+            let connected_at = tokio::time::Instant::now();
+            if let Err(error) = run_rendezvous_control_connection(&config, &sender).await {
+                eprintln!("rendezvous control connection to {} failed: {error:?}", config.relay_addr);
+            }
+            if sender.is_closed() {
+                return;
+            }
+            if connected_at.elapsed() >= RENDEZVOUS_BACKOFF_RESET_THRESHOLD {
+                backoff = RENDEZVOUS_INITIAL_BACKOFF;
+            }
+            let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..=backoff.as_millis() as u64));
+            tokio::time::sleep(jitter).await;
+            backoff = (backoff * 2).min(RENDEZVOUS_MAX_BACKOFF);
+        }
+    });
+    Box::new(ReceiverStream::new(receiver))
+}
+
+// Opens the control connection to the relay, identifies this app with a handshake, then loops
+// sending heartbeats and opening a new data connection for each `OPEN <token>` signal the relay
+// sends, handing the resulting socket off to `sender`. Returns once the control connection drops
+// so the caller can reconnect with backoff.
+async fn run_rendezvous_control_connection(
+    config: &RendezvousCommunicationChannel,
+    sender: &mpsc::Sender<std::io::Result<TcpStream>>,
+) -> anyhow::Result<()> {
+    let control = TcpStream::connect(&config.relay_addr)
+        .await
+        .context("couldn't connect to relay")?;
+    let (read_half, mut write_half) = control.into_split();
+    write_half
+        .write_all(format!("HELLO {}\n", config.node_id).as_bytes())
+        .await
+        .context("couldn't send handshake to relay")?;
+
+    let mut lines = BufReader::new(read_half).lines();
+    let mut heartbeat = tokio::time::interval(RENDEZVOUS_HEARTBEAT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                write_half
+                    .write_all(b"HEARTBEAT\n")
+                    .await
+                    .context("couldn't send heartbeat to relay")?;
+            }
+            line = lines.next_line() => {
+                let Some(line) = line.context("control connection read failed")? else {
+                    // The relay closed the control connection; let the caller reconnect.
+                    return Ok(());
+                };
+                if let Some(token) = line.strip_prefix("OPEN ") {
+                    let mut data_connection = TcpStream::connect(&config.relay_addr)
+                        .await
+                        .context("couldn't open data connection to relay")?;
+                    data_connection
+                        .write_all(format!("DATA {token}\n").as_bytes())
+                        .await
+                        .context("couldn't send data handoff to relay")?;
+                    if sender.send(Ok(data_connection)).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Bounds how much 0-RTT early data a resumed TLS 1.3 session may carry, in bytes. 0-RTT data is
+// not forward-secret and can be replayed by an attacker who captures it, so this is sized to
+// cover only the first request frame's length prefix plus a small request, not set to the
+// protocol maximum: an unbounded limit would let an unauthenticated client hold open up to 4 GiB
+// of pre-handshake data per connection (a memory-exhaustion vector) and would let a captured
+// 0-RTT request be replayed in full against any handler that isn't idempotent.
+const TLS_MAX_EARLY_DATA_SIZE: u32 = 16 * 1024;
+
+// Builds a `TlsAcceptor` from the PEM-encoded server cert/key (and, optionally, a client-CA
+// bundle that enables mutual TLS) carried in the `TlsCommunicationChannel` config.
+fn build_tls_acceptor(config: &TlsCommunicationChannel) -> anyhow::Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut config.server_cert_pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .context("couldn't parse server certificate PEM")?;
+    let key = rustls_pemfile::read_one(&mut config.server_key_pem.as_slice())
+        .context("couldn't parse server key PEM")?
+        .and_then(|item| match item {
+            Item::Pkcs1Key(key) => Some(key.into()),
+            Item::Pkcs8Key(key) => Some(key.into()),
+            Item::Sec1Key(key) => Some(key.into()),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("no private key found in server key PEM"))?;
+
+    let builder = rustls::ServerConfig::builder();
+    let mut server_config = if config.client_ca_pem.is_empty() {
+        builder.with_no_client_auth().with_single_cert(certs, key)?
+    } else {
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut config.client_ca_pem.as_slice()) {
+            roots.add(cert.context("couldn't parse client CA certificate PEM")?)?;
+        }
+        let client_cert_verifier =
+            rustls::server::WebPkiClientVerifier::builder(Arc::new(roots)).build()?;
+        builder
+            .with_client_cert_verifier(client_cert_verifier)
+            .with_single_cert(certs, key)?
+    };
+    // Accept 0-RTT payloads on resumed sessions, bounded since they're replayable (see
+    // `TLS_MAX_EARLY_DATA_SIZE`).
+    server_config.max_early_data_size = TLS_MAX_EARLY_DATA_SIZE;
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
@@ -219,13 +502,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 if config.port == 0 {
                     config.port = OAK_FUNCTIONS_CONTAINERS_APP_PORT.into();
                 }
-                let addr =
-                    SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), config.port.try_into()?);
-                let listener = TcpListener::bind(addr).await?;
-                serve(
+                let (addr, stream) = bind_tcp_channel(&config).await?;
+                dispatch_serve(
+                    application_config.streaming,
                     addr,
                     application_config.handler_type(),
-                    Box::new(TcpListenerStream::new(listener)),
+                    stream,
+                    encryption_key_handle,
+                    meter,
+                )
+                .await
+            }
+            CommunicationChannel::TlsChannel(config) => {
+                let mut config = config.clone();
+                if config.port == 0 {
+                    config.port = OAK_FUNCTIONS_CONTAINERS_APP_PORT.into();
+                }
+                let tls_acceptor = build_tls_acceptor(&config)?;
+                let (addr, tcp_stream) = bind_tcp_channel(&TcpCommunicationChannel {
+                    port: config.port,
+                    bind_address: config.bind_address.clone(),
+                    ..Default::default()
+                })
+                .await?;
+                let tls_stream = tcp_stream.then(move |stream| {
+                    let tls_acceptor = tls_acceptor.clone();
+                    Box::pin(async move { tls_acceptor.accept(stream?).await })
+                });
+                dispatch_serve(
+                    application_config.streaming,
+                    addr,
+                    application_config.handler_type(),
+                    Box::new(tls_stream),
+                    encryption_key_handle,
+                    meter,
+                )
+                .await
+            }
+            CommunicationChannel::RendezvousChannel(config) => {
+                let config = config.clone();
+                let relay_addr = config.relay_addr.clone();
+                dispatch_serve(
+                    application_config.streaming,
+                    relay_addr,
+                    application_config.handler_type(),
+                    rendezvous_stream(config),
                     encryption_key_handle,
                     meter,
                 )
@@ -238,7 +559,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 let addr = VsockAddr::new(tokio_vsock::VMADDR_CID_ANY, config.port);
                 let listener = VsockListener::bind(addr)?;
-                serve(
+                dispatch_serve(
+                    application_config.streaming,
                     addr,
                     application_config.handler_type(),
                     Box::new(listener.incoming()),
@@ -0,0 +1,120 @@
+//
+// Copyright 2023 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use oak_crypto::encryption_key::AsyncEncryptionKeyHandle;
+use opentelemetry::metrics::{Meter, MeterProvider};
+use tokio::sync::mpsc;
+
+use crate::{Handler, StreamingHandler};
+
+/// Maximum size, in bytes, of a single streamed response chunk. The response is computed in full
+/// before it is split, so this bounds the size of each chunk written to the connection, not the
+/// memory held for the response.
+const MAX_STREAMING_CHUNK_BYTES: usize = 4096;
+
+/// A `Handler` that runs requests against natively compiled (non-Wasm) application logic,
+/// selected via `HandlerType::HandlerNative` in the `ApplicationConfig`.
+pub struct NativeHandler {
+    #[allow(dead_code)]
+    encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
+    requests_handled: Arc<AtomicU64>,
+}
+
+impl NativeHandler {
+    /// Computes the response for `request`. Framing, chunking, and encryption are the caller's
+    /// concern; this only implements the native application logic itself.
+    fn process(&self, request: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.requests_handled.fetch_add(1, Ordering::Relaxed);
+        Ok(request.to_vec())
+    }
+}
+
+/// Splits `response` into chunks of at most `MAX_STREAMING_CHUNK_BYTES` bytes each, in order,
+/// for `StreamingHandler::handle_invoke_streaming` to send one at a time. Pulled out of that
+/// method so it can be exercised without a `NativeHandler` instance.
+fn chunk_response(response: &[u8]) -> Vec<Vec<u8>> {
+    response.chunks(MAX_STREAMING_CHUNK_BYTES).map(<[u8]>::to_vec).collect()
+}
+
+impl Handler for NativeHandler {
+    async fn create(
+        encryption_key_handle: Box<dyn AsyncEncryptionKeyHandle + Send + Sync>,
+        meter: Meter,
+    ) -> anyhow::Result<Self> {
+        let requests_handled = Arc::new(AtomicU64::new(0));
+        let observed_requests_handled = requests_handled.clone();
+        meter
+            .u64_observable_counter("native_handler_requests_handled")
+            .with_description("Number of requests handled by the native handler")
+            .with_callback(move |counter| {
+                counter.observe(observed_requests_handled.load(Ordering::Relaxed), &[]);
+            })
+            .try_init()?;
+        Ok(Self { encryption_key_handle, requests_handled })
+    }
+
+    async fn handle_invoke(&self, request: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        self.process(&request)
+    }
+}
+
+impl StreamingHandler for NativeHandler {
+    async fn handle_invoke_streaming(
+        &self,
+        request: Vec<u8>,
+    ) -> anyhow::Result<mpsc::Receiver<anyhow::Result<Vec<u8>>>> {
+        let response = self.process(&request)?;
+        let (sender, receiver) = mpsc::channel(4);
+        tokio::spawn(async move {
+            for chunk in chunk_response(&response) {
+                if sender.send(Ok(chunk)).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_within_one_chunk_stays_whole() {
+        let response = vec![7u8; MAX_STREAMING_CHUNK_BYTES];
+        assert_eq!(chunk_response(&response), vec![response]);
+    }
+
+    #[test]
+    fn response_over_one_chunk_splits_in_order() {
+        let response: Vec<u8> = (0..MAX_STREAMING_CHUNK_BYTES + 1).map(|i| i as u8).collect();
+        let chunks = chunk_response(&response);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), MAX_STREAMING_CHUNK_BYTES);
+        assert_eq!(chunks[1].len(), 1);
+        assert_eq!(chunks.concat(), response);
+    }
+
+    #[test]
+    fn empty_response_yields_no_chunks() {
+        assert!(chunk_response(&[]).is_empty());
+    }
+}
@@ -16,13 +16,17 @@
 
 mod page;
 mod stage0;
+mod tdx;
 mod vmsa;
 
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use log::trace;
 use page::PageInfo;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tdx::MrTd;
 use x86_64::structures::paging::{PageSize, Size4KiB};
 
 use crate::{
@@ -30,6 +34,45 @@ use crate::{
     vmsa::{get_ap_vmsa, get_boot_vmsa, VMSA_ADDRESS},
 };
 
+/// The confidential-VM platform to compute the launch measurement for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum Platform {
+    /// AMD SEV-SNP, producing the launch digest.
+    #[default]
+    Sev,
+    /// Intel TDX, producing the build-time measurement (MRTD).
+    Tdx,
+}
+
+/// The output format for the computed measurement.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// A human-readable summary line, as printed historically.
+    #[default]
+    Text,
+    /// A structured document suitable for CI pipelines and release-signing jobs.
+    Json,
+}
+
+/// A structured report of the computed measurement, emitted with `--format json`.
+#[derive(Serialize)]
+struct MeasurementReport {
+    measurement: String,
+    mrtd: Option<String>,
+    vcpu_count: usize,
+    legacy_boot: bool,
+    stage0_path: String,
+    stage0_hash: String,
+    snp_pages: Vec<SnpPageContribution>,
+}
+
+/// The number of pages of a given SNP page type folded into the measurement.
+#[derive(Serialize)]
+struct SnpPageContribution {
+    page_type: String,
+    page_count: usize,
+}
+
 /// The default workspace-relative path to the Stage 0 firmware ROM image.
 const DEFAULT_STAGE0_ROM: &str = "stage0_bin/target/x86_64-unknown-none/release/stage0_bin";
 
@@ -46,6 +89,26 @@ struct Cli {
         default_value_t = 1
     )]
     vcpu_count: usize,
+    #[arg(
+        long,
+        value_enum,
+        help = "The confidential-VM platform to compute the measurement for",
+        default_value_t = Platform::Sev
+    )]
+    platform: Platform,
+    #[arg(
+        long,
+        value_enum,
+        help = "The format to emit the measurement in",
+        default_value_t = OutputFormat::Text
+    )]
+    format: OutputFormat,
+    #[arg(
+        long,
+        help = "A reference measurement to compare the computed one against; the tool exits \
+                non-zero on mismatch"
+    )]
+    expected: Option<String>,
 }
 
 impl Cli {
@@ -71,7 +134,11 @@ fn main() -> anyhow::Result<()> {
         page_info.update_from_data(stage0.legacy_shadow_bytes(), stage0.legacy_start_address);
     }
 
+    let mut snp_page_counts: BTreeMap<String, usize> = BTreeMap::new();
     for snp_page in stage0.get_snp_pages() {
+        *snp_page_counts
+            .entry(format!("{:?}", snp_page.page_type))
+            .or_default() += snp_page.page_count as usize;
         for page_number in 0..snp_page.page_count {
             page_info.update_from_snp_page(
                 snp_page.page_type.clone(),
@@ -93,9 +160,72 @@ fn main() -> anyhow::Result<()> {
 
     trace!("raw measurement: {:?}", page_info.digest_cur);
 
-    println!(
-        "Attestation Measurement: {}",
-        hex::encode(page_info.digest_cur)
-    );
+    let measurement = hex::encode(page_info.digest_cur);
+
+    let mrtd = if cli.platform == Platform::Tdx {
+        let mut mrtd = MrTd::new();
+
+        // Add the Stage 0 firmware ROM image.
+        mrtd.add_measured_data(stage0.rom_bytes(), stage0.start_address);
+        if cli.legacy_boot {
+            // Add the legacy boot shadow of the Stage 0 firmware ROM image.
+            mrtd.add_measured_data(stage0.legacy_shadow_bytes(), stage0.legacy_start_address);
+        }
+
+        // The remaining SNP pages (VMSA aside, which TDX doesn't fold into MRTD) are added but
+        // not content-measured, matching how they're treated as reserved/zero pages here.
+        for snp_page in stage0.get_snp_pages() {
+            for page_number in 0..snp_page.page_count {
+                mrtd.add_page(snp_page.start_address + (page_number as u64) * Size4KiB::SIZE);
+            }
+        }
+
+        Some(hex::encode(mrtd.finish()))
+    } else {
+        None
+    };
+
+    match cli.format {
+        OutputFormat::Text => {
+            println!("Attestation Measurement: {measurement}");
+            if let Some(mrtd) = &mrtd {
+                println!("MRTD: {mrtd}");
+            }
+        }
+        OutputFormat::Json => {
+            let report = MeasurementReport {
+                measurement: measurement.clone(),
+                mrtd: mrtd.clone(),
+                vcpu_count: cli.vcpu_count,
+                legacy_boot: cli.legacy_boot,
+                stage0_path: cli.stage0_path().display().to_string(),
+                stage0_hash: hex::encode(Sha256::digest(stage0.rom_bytes())),
+                snp_pages: snp_page_counts
+                    .into_iter()
+                    .map(|(page_type, page_count)| SnpPageContribution { page_type, page_count })
+                    .collect(),
+            };
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+
+    if let Some(expected) = &cli.expected {
+        match cli.platform {
+            Platform::Sev => {
+                if !measurement.eq_ignore_ascii_case(expected) {
+                    anyhow::bail!(
+                        "measurement mismatch: computed {measurement} but expected {expected}"
+                    );
+                }
+            }
+            Platform::Tdx => {
+                let mrtd = mrtd.as_ref().expect("mrtd is always computed for Platform::Tdx");
+                if !mrtd.eq_ignore_ascii_case(expected) {
+                    anyhow::bail!("measurement mismatch: computed MRTD {mrtd} but expected {expected}");
+                }
+            }
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,141 @@
+//
+// Copyright 2022 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Computation of the Intel TDX build-time measurement register (MRTD).
+//!
+//! MRTD is a SHA-384 digest that is extended once per page as the guest TD is built, in GPA
+//! order. Each added page contributes a `MEM.PAGE.ADD` record; pages whose contents are measured
+//! additionally contribute, per 256-byte chunk, an `MR.EXTEND` record followed by the chunk data
+//! itself. See the Intel TDX Module specification for the authoritative description of this
+//! extension log.
+
+use sha2::{Digest, Sha384};
+use x86_64::structures::paging::{PageSize, Size4KiB};
+
+/// Records are zero-padded to this width; the first `TAG_SIZE` bytes hold the ASCII tag, the next
+/// 8 bytes hold a little-endian GPA or GPA offset, and the remainder is reserved, zeroed padding.
+const RECORD_SIZE: usize = 128;
+/// Width of the tag field. The GPA/offset always starts right after it, at a fixed byte offset
+/// regardless of how long the tag string itself is.
+const TAG_SIZE: usize = 16;
+/// `MR.EXTEND` records cover the page data in chunks of this size.
+const EXTEND_CHUNK_SIZE: usize = 256;
+
+const MEM_PAGE_ADD_TAG: &[u8] = b"MEM.PAGE.ADD";
+const MR_EXTEND_TAG: &[u8] = b"MR.EXTEND";
+
+fn tagged_record(tag: &[u8], value: u64) -> [u8; RECORD_SIZE] {
+    assert!(tag.len() <= TAG_SIZE, "tag must fit within the fixed tag field");
+    let mut record = [0u8; RECORD_SIZE];
+    record[..tag.len()].copy_from_slice(tag);
+    record[TAG_SIZE..TAG_SIZE + 8].copy_from_slice(&value.to_le_bytes());
+    record
+}
+
+/// Accumulates the MRTD measurement as pages are added to the guest TD.
+pub struct MrTd {
+    hasher: Sha384,
+}
+
+impl MrTd {
+    pub fn new() -> Self {
+        MrTd { hasher: Sha384::new() }
+    }
+
+    /// Records that a 4 KiB page at `gpa` was added to the TD, without measuring its contents.
+    pub fn add_page(&mut self, gpa: u64) {
+        self.hasher.update(tagged_record(MEM_PAGE_ADD_TAG, gpa));
+    }
+
+    /// Records that a 4 KiB page at `gpa` was added to the TD and measures its contents, which
+    /// must be exactly one page (`Size4KiB::SIZE` bytes) long.
+    pub fn add_measured_page(&mut self, gpa: u64, data: &[u8]) {
+        assert_eq!(
+            data.len() as u64,
+            Size4KiB::SIZE,
+            "measured TDX pages must be exactly one 4 KiB page"
+        );
+        self.add_page(gpa);
+        for (index, chunk) in data.chunks(EXTEND_CHUNK_SIZE).enumerate() {
+            let offset = gpa + (index * EXTEND_CHUNK_SIZE) as u64;
+            self.hasher.update(tagged_record(MR_EXTEND_TAG, offset));
+            self.hasher.update(chunk);
+        }
+    }
+
+    /// Measures `data` as a sequence of whole 4 KiB pages starting at `start_address`, padding
+    /// the final partial page with zeroes the same way the SEV-SNP measurement does.
+    pub fn add_measured_data(&mut self, data: &[u8], start_address: u64) {
+        let page_size = Size4KiB::SIZE as usize;
+        for (index, chunk) in data.chunks(page_size).enumerate() {
+            let gpa = start_address + (index * page_size) as u64;
+            if chunk.len() == page_size {
+                self.add_measured_page(gpa, chunk);
+            } else {
+                let mut page = vec![0u8; page_size];
+                page[..chunk.len()].copy_from_slice(chunk);
+                self.add_measured_page(gpa, &page);
+            }
+        }
+    }
+
+    /// Finalizes the measurement, returning the 48-byte MRTD digest.
+    pub fn finish(self) -> [u8; 48] {
+        self.hasher.finalize().into()
+    }
+}
+
+impl Default for MrTd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unmeasured_page_matches_known_digest() {
+        let mut mrtd = MrTd::new();
+        mrtd.add_page(0x1000);
+        assert_eq!(
+            hex::encode(mrtd.finish()),
+            "fcdabf6fdf38b87d2e3a89b1ab68c242abb261dffa70ef6f1dc2220c2752d2729cdf1be92afc2e0e4297f04e2b629552"
+        );
+    }
+
+    #[test]
+    fn measured_data_smaller_than_a_page_matches_known_digest() {
+        let mut mrtd = MrTd::new();
+        mrtd.add_measured_data(b"hello tdx mrtd test data", 0x2000);
+        assert_eq!(
+            hex::encode(mrtd.finish()),
+            "77999847d3973790eeaf474f8b2f47e4de615cf2aac7199b3fb66632c6f5290fbee64445809f8f33ec3f1490917438b1"
+        );
+    }
+
+    #[test]
+    fn gpa_lands_at_a_fixed_offset_regardless_of_tag_length() {
+        let shorter = tagged_record(MR_EXTEND_TAG, 0x4242);
+        let longer = tagged_record(MEM_PAGE_ADD_TAG, 0x4242);
+        assert_eq!(
+            shorter[TAG_SIZE..TAG_SIZE + 8],
+            longer[TAG_SIZE..TAG_SIZE + 8],
+            "GPA/offset bytes must start at the same fixed position for every tag"
+        );
+    }
+}